@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use segment::types::SeqNumberType;
+
+use crate::collection_builder::snapshot::{SegmentState, Snapshot};
+use crate::collection_builder::wal_recovery::{SequenceGap, WalRecoveryState};
+use crate::segment_manager::holders::segment_holder::SegmentId;
+
+/// Loads the newest snapshot in `segments_path` (if any) and replays the WAL
+/// against it. Each `SegmentState.version` seeds that segment's own
+/// catch-up point via `WalRecoveryState`, so a segment that persisted
+/// behind its peers still gets the entries it's missing rather than being
+/// skipped by a single collection-wide cutoff. Call this on collection boot
+/// instead of replaying the whole WAL unconditionally.
+pub fn load_collection<Op>(
+    segments_path: &Path,
+    wal_entries: impl IntoIterator<Item = (SeqNumberType, SegmentId, Op)>,
+    strict_wal_recovery: bool,
+    mut apply_operation: impl FnMut(SeqNumberType, Op),
+) -> Result<Option<Snapshot>, SequenceGap> {
+    let snapshot = Snapshot::load_latest(segments_path);
+    let floor = snapshot.as_ref().map(|s| s.min_op_num).unwrap_or(0);
+
+    let mut recovery = WalRecoveryState::resume_from(floor, strict_wal_recovery);
+    if let Some(s) = &snapshot {
+        for (&segment_id, state) in &s.segments {
+            recovery.record_segment_sequence(segment_id, state.version);
+        }
+    }
+
+    let remaining = wal_entries.into_iter().filter(|(op_num, _, _)| *op_num > floor);
+    recovery.replay(remaining, &mut apply_operation)?;
+
+    Ok(snapshot)
+}
+
+/// Builds a fresh snapshot from the current per-segment versions and persists
+/// it, returning the op_num the WAL can be safely truncated below. Call this
+/// from the flush loop on the period given by `OptimizersConfig::flush_interval_sec`.
+pub fn checkpoint(
+    segments_path: &Path,
+    segment_versions: impl IntoIterator<Item = (SegmentId, SegmentState)>,
+    compress: bool,
+) -> std::io::Result<SeqNumberType> {
+    let segments: HashMap<SegmentId, SegmentState> = segment_versions.into_iter().collect();
+    // Truncating below the fastest segment's version would drop entries a
+    // lagging segment still needs, so the safe cutoff is the slowest one.
+    let safe_truncate_point = segments.values().map(|s| s.version).min().unwrap_or(0);
+
+    Snapshot::new(safe_truncate_point, segments).save(segments_path, compress)?;
+    Ok(safe_truncate_point)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_load_collection_replays_only_after_snapshot() {
+        let dir = TempDir::new("collection_dir").unwrap();
+
+        let mut segments = HashMap::new();
+        segments.insert(1, SegmentState { version: 5, path: dir.path().join("1") });
+        Snapshot::new(5, segments).save(dir.path(), false).unwrap();
+
+        let wal_entries = vec![(4, 1, "stale"), (6, 1, "a"), (7, 1, "b")];
+        let mut applied = Vec::new();
+        let snapshot = load_collection(dir.path(), wal_entries, true, |op_num, op| applied.push((op_num, op))).unwrap();
+
+        assert_eq!(snapshot.unwrap().min_op_num, 5);
+        assert_eq!(applied, vec![(6, "a"), (7, "b")]);
+    }
+
+    #[test]
+    fn test_load_collection_without_snapshot_replays_everything() {
+        let dir = TempDir::new("collection_dir").unwrap();
+
+        let wal_entries = vec![(1, 1, "a"), (2, 1, "b")];
+        let mut applied = Vec::new();
+        let snapshot = load_collection(dir.path(), wal_entries, true, |op_num, op| applied.push((op_num, op))).unwrap();
+
+        assert!(snapshot.is_none());
+        assert_eq!(applied, vec![(1, "a"), (2, "b")]);
+    }
+
+    #[test]
+    fn test_checkpoint_roundtrips_through_load_collection() {
+        let dir = TempDir::new("collection_dir").unwrap();
+
+        let mut segments = HashMap::new();
+        segments.insert(1, SegmentState { version: 3, path: dir.path().join("1") });
+        segments.insert(2, SegmentState { version: 7, path: dir.path().join("2") });
+
+        let min_op_num = checkpoint(dir.path(), segments, true).unwrap();
+        assert_eq!(min_op_num, 3);
+
+        // Ops 5-7 are already reflected in segment 2's persisted version, so
+        // they still have to appear in the WAL for contiguity but shouldn't
+        // be re-applied.
+        let wal_entries = vec![
+            (4, 1, "a"),
+            (5, 2, "already-applied"),
+            (6, 2, "already-applied"),
+            (7, 2, "already-applied"),
+            (8, 2, "b"),
+        ];
+        let mut applied = Vec::new();
+        let snapshot = load_collection(dir.path(), wal_entries, true, |op_num, op| applied.push((op_num, op))).unwrap();
+
+        assert_eq!(snapshot.unwrap().min_op_num, 3);
+        assert_eq!(applied, vec![(4, "a"), (8, "b")]);
+    }
+
+    #[test]
+    fn test_load_collection_catches_up_a_lagging_segment() {
+        // Segment 1 persisted up to op 3, segment 2 up to op 7 -- a single
+        // collection-wide cutoff of 7 would have silently dropped ops 4-7
+        // for segment 1.
+        let dir = TempDir::new("collection_dir").unwrap();
+
+        let mut segments = HashMap::new();
+        segments.insert(1, SegmentState { version: 3, path: dir.path().join("1") });
+        segments.insert(2, SegmentState { version: 7, path: dir.path().join("2") });
+        Snapshot::new(3, segments).save(dir.path(), false).unwrap();
+
+        let wal_entries = vec![
+            (4, 1, "seg1-op4"),
+            (5, 1, "seg1-op5"),
+            (6, 1, "seg1-op6"),
+            (7, 1, "seg1-op7"),
+            (8, 2, "seg2-op8"),
+        ];
+        let mut applied = Vec::new();
+        load_collection(dir.path(), wal_entries, true, |op_num, op| applied.push((op_num, op))).unwrap();
+
+        assert_eq!(
+            applied,
+            vec![
+                (4, "seg1-op4"),
+                (5, "seg1-op5"),
+                (6, "seg1-op6"),
+                (7, "seg1-op7"),
+                (8, "seg2-op8"),
+            ]
+        );
+    }
+}
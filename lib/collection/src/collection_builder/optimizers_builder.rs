@@ -8,17 +8,20 @@ use serde::{Deserialize, Serialize};
 use schemars::{JsonSchema};
 use crate::segment_manager::optimizers::indexing_optimizer::IndexingOptimizer;
 use crate::segment_manager::optimizers::segment_optimizer::OptimizerThresholds;
+use crate::segment_manager::optimizers::merge_policy::MergePolicyConfig;
 
 
 #[derive(Debug, Deserialize, Serialize, JsonSchema, Clone)]
 pub struct OptimizersConfig {
     pub deleted_threshold: f64,
     pub vacuum_min_vector_number: usize,
-    pub max_segment_number: usize,
     pub memmap_threshold: usize,
     pub indexing_threshold: usize,
     pub payload_indexing_threshold: usize,
     pub flush_interval_sec: u64,
+    /// Which `MergePolicy` selects merge candidates, and its parameters.
+    #[serde(default)]
+    pub merge_policy: MergePolicyConfig,
 }
 
 
@@ -36,6 +39,8 @@ pub fn build_optimizers(
         payload_indexing_threshold: optimizers_config.payload_indexing_threshold
     };
 
+    let merge_policy = optimizers_config.merge_policy.build();
+
     Arc::new(vec![
         Box::new(
             IndexingOptimizer::new(
@@ -47,7 +52,7 @@ pub fn build_optimizers(
         ),
         Box::new(
             MergeOptimizer::new(
-                optimizers_config.max_segment_number,
+                merge_policy,
                 threshold_config.clone(),
                 segments_path.clone(),
                 temp_segments_path.clone(),
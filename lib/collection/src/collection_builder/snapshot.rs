@@ -0,0 +1,133 @@
+//! Snapshot/checkpoint support for fast restart. See `collection_loader` for
+//! how this is used on boot and from the flush loop.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use segment::types::SeqNumberType;
+use serde::{Deserialize, Serialize};
+
+use crate::segment_manager::holders::segment_holder::SegmentId;
+
+pub const SNAPSHOT_FILE_NAME: &str = "snapshot.json";
+pub const SNAPSHOT_FILE_NAME_COMPRESSED: &str = "snapshot.json.zst";
+
+/// A segment's last-flushed op_num and on-disk location, without opening it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SegmentState {
+    pub version: SeqNumberType,
+    pub path: PathBuf,
+}
+
+/// A point-in-time record of every segment a collection owns.
+///
+/// `min_op_num` is the lowest `version` among `segments` -- the point below
+/// which every segment is known to have caught up, so it's both the floor
+/// WAL replay can skip below and the point the WAL can be truncated below.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub min_op_num: SeqNumberType,
+    pub segments: HashMap<SegmentId, SegmentState>,
+}
+
+impl Snapshot {
+    pub fn new(min_op_num: SeqNumberType, segments: HashMap<SegmentId, SegmentState>) -> Self {
+        Snapshot { min_op_num, segments }
+    }
+
+    /// Writes to a temp path, fsyncs, then renames into place, so a crash
+    /// mid-write never corrupts a previously-written snapshot.
+    pub fn save(&self, segments_path: &Path, compress: bool) -> std::io::Result<()> {
+        let file_name = if compress { SNAPSHOT_FILE_NAME_COMPRESSED } else { SNAPSHOT_FILE_NAME };
+        let target_path = segments_path.join(file_name);
+        let tmp_path = segments_path.join(format!("{}.tmp", file_name));
+
+        let raw = serde_json::to_vec(self)?;
+        let payload = if compress {
+            zstd::encode_all(raw.as_slice(), 0)?
+        } else {
+            raw
+        };
+
+        {
+            let mut tmp_file = fs::File::create(&tmp_path)?;
+            tmp_file.write_all(&payload)?;
+            tmp_file.sync_all()?;
+        }
+        fs::rename(&tmp_path, &target_path)?;
+        Ok(())
+    }
+
+    /// Newest valid snapshot in `segments_path` by file modification time,
+    /// preferring the compressed form on a tie. `None` if neither file exists
+    /// or parses.
+    pub fn load_latest(segments_path: &Path) -> Option<Self> {
+        // On equal times `max_by_key` keeps the last, so the compressed file goes last.
+        let candidates = [
+            (segments_path.join(SNAPSHOT_FILE_NAME), false),
+            (segments_path.join(SNAPSHOT_FILE_NAME_COMPRESSED), true),
+        ];
+
+        candidates
+            .iter()
+            .filter_map(|(path, compressed)| {
+                let modified = fs::metadata(path).and_then(|meta| meta.modified()).ok()?;
+                Some((modified, Self::read(path, *compressed)?))
+            })
+            .max_by_key(|(modified, _)| *modified)
+            .map(|(_, snapshot)| snapshot)
+    }
+
+    fn read(path: &Path, compressed: bool) -> Option<Self> {
+        let mut raw = Vec::new();
+        fs::File::open(path).ok()?.read_to_end(&mut raw).ok()?;
+        let raw = if compressed {
+            zstd::decode_all(raw.as_slice()).ok()?
+        } else {
+            raw
+        };
+        serde_json::from_slice(&raw).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_save_and_load_roundtrip() {
+        let dir = TempDir::new("snapshot_dir").unwrap();
+
+        let mut segments = HashMap::new();
+        segments.insert(1, SegmentState { version: 42, path: dir.path().join("1") });
+
+        let snapshot = Snapshot::new(42, segments);
+        snapshot.save(dir.path(), false).unwrap();
+
+        let loaded = Snapshot::load_latest(dir.path()).unwrap();
+        assert_eq!(loaded, snapshot);
+    }
+
+    #[test]
+    fn test_save_and_load_compressed() {
+        let dir = TempDir::new("snapshot_dir").unwrap();
+
+        let mut segments = HashMap::new();
+        segments.insert(7, SegmentState { version: 100, path: dir.path().join("7") });
+
+        let snapshot = Snapshot::new(100, segments);
+        snapshot.save(dir.path(), true).unwrap();
+
+        let loaded = Snapshot::load_latest(dir.path()).unwrap();
+        assert_eq!(loaded, snapshot);
+    }
+
+    #[test]
+    fn test_load_latest_missing_is_none() {
+        let dir = TempDir::new("snapshot_dir").unwrap();
+        assert!(Snapshot::load_latest(dir.path()).is_none());
+    }
+}
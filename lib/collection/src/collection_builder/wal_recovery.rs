@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use segment::types::SeqNumberType;
+
+use crate::segment_manager::holders::segment_holder::SegmentId;
+
+/// Mismatch between the op_num a replaying WAL entry carries and the one
+/// expected to come next.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SequenceGap {
+    pub expected: SeqNumberType,
+    pub actual: SeqNumberType,
+}
+
+impl fmt::Display for SequenceGap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "WAL sequence gap: expected op_num {}, got {}", self.expected, self.actual)
+    }
+}
+
+impl Error for SequenceGap {}
+
+/// Tracks WAL replay progress and validates that applied op_nums are contiguous.
+pub struct WalRecoveryState {
+    last_applied: Option<SeqNumberType>,
+    /// Last op_num each segment is known to have absorbed.
+    segment_sequences: HashMap<SegmentId, SeqNumberType>,
+    strict: bool,
+}
+
+impl WalRecoveryState {
+    pub fn new(strict: bool) -> Self {
+        WalRecoveryState {
+            last_applied: None,
+            segment_sequences: HashMap::new(),
+            strict,
+        }
+    }
+
+    /// Resumes from a loaded snapshot, validating continuity right after `max_op_num`.
+    pub fn resume_from(max_op_num: SeqNumberType, strict: bool) -> Self {
+        let mut state = Self::new(strict);
+        state.last_applied = Some(max_op_num);
+        state
+    }
+
+    /// Checks `op_num` is exactly `last_applied + 1`. In strict mode a gap is
+    /// returned as an error; in lenient mode it's logged and replay continues.
+    pub fn validate(&mut self, op_num: SeqNumberType) -> Result<(), SequenceGap> {
+        if let Some(last) = self.last_applied {
+            let expected = last + 1;
+            if op_num != expected {
+                let gap = SequenceGap { expected, actual: op_num };
+                if self.strict {
+                    return Err(gap);
+                }
+                log::warn!("{}, continuing in lenient WAL recovery mode", gap);
+            }
+        }
+        self.last_applied = Some(op_num);
+        Ok(())
+    }
+
+    pub fn record_segment_sequence(&mut self, segment_id: SegmentId, op_num: SeqNumberType) {
+        self.segment_sequences.insert(segment_id, op_num);
+    }
+
+    pub fn last_sequence(&self, segment_id: SegmentId) -> Option<SeqNumberType> {
+        self.segment_sequences.get(&segment_id).copied()
+    }
+
+    /// Whether `segment_id` is behind the WAL tail and needs catch-up replay.
+    pub fn needs_catch_up(&self, segment_id: SegmentId, wal_tail: SeqNumberType) -> bool {
+        self.last_sequence(segment_id).map_or(true, |seq| seq < wal_tail)
+    }
+
+    /// Replays `wal_entries` in order. Continuity is validated globally,
+    /// while `needs_catch_up` decides per segment whether `apply` actually
+    /// needs to run, so a segment that persisted behind its peers still
+    /// gets the entries it's missing without re-applying ones it already
+    /// has. This is the actual recovery loop callers (e.g.
+    /// `collection_loader::load_collection`) drive on startup.
+    pub fn replay<Op>(
+        &mut self,
+        wal_entries: impl IntoIterator<Item = (SeqNumberType, SegmentId, Op)>,
+        mut apply: impl FnMut(SeqNumberType, Op),
+    ) -> Result<(), SequenceGap> {
+        for (op_num, segment_id, operation) in wal_entries {
+            self.validate(op_num)?;
+            if self.needs_catch_up(segment_id, op_num) {
+                apply(op_num, operation);
+                self.record_segment_sequence(segment_id, op_num);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contiguous_sequence_is_accepted() {
+        let mut state = WalRecoveryState::new(true);
+        assert!(state.validate(1).is_ok());
+        assert!(state.validate(2).is_ok());
+        assert!(state.validate(3).is_ok());
+    }
+
+    #[test]
+    fn test_strict_mode_fails_on_gap() {
+        let mut state = WalRecoveryState::new(true);
+        state.validate(1).unwrap();
+        let err = state.validate(3).unwrap_err();
+        assert_eq!(err, SequenceGap { expected: 2, actual: 3 });
+    }
+
+    #[test]
+    fn test_lenient_mode_continues_past_gap() {
+        let mut state = WalRecoveryState::new(false);
+        state.validate(1).unwrap();
+        assert!(state.validate(3).is_ok());
+        assert!(state.validate(4).is_ok());
+    }
+
+    #[test]
+    fn test_resume_from_snapshot_validates_next_entry() {
+        let mut state = WalRecoveryState::resume_from(10, true);
+        assert!(state.validate(11).is_ok());
+        let mut state = WalRecoveryState::resume_from(10, true);
+        assert!(state.validate(12).is_err());
+    }
+
+    #[test]
+    fn test_needs_catch_up() {
+        let mut state = WalRecoveryState::new(true);
+        state.record_segment_sequence(1, 5);
+        assert!(state.needs_catch_up(1, 10));
+        assert!(!state.needs_catch_up(1, 5));
+        assert!(state.needs_catch_up(2, 1));
+    }
+
+    #[test]
+    fn test_replay_applies_entries_in_order() {
+        let mut state = WalRecoveryState::new(true);
+        let mut applied = Vec::new();
+        state.replay(vec![(1, 1, "a"), (2, 1, "b"), (3, 1, "c")], |op_num, op| applied.push((op_num, op))).unwrap();
+        assert_eq!(applied, vec![(1, "a"), (2, "b"), (3, "c")]);
+    }
+
+    #[test]
+    fn test_replay_stops_on_gap() {
+        let mut state = WalRecoveryState::new(true);
+        let mut applied = Vec::new();
+        let err = state.replay(vec![(1, 1, "a"), (3, 1, "c")], |op_num, op| applied.push((op_num, op))).unwrap_err();
+        assert_eq!(err, SequenceGap { expected: 2, actual: 3 });
+        assert_eq!(applied, vec![(1, "a")]);
+    }
+
+    #[test]
+    fn test_replay_skips_entries_a_segment_already_has() {
+        let mut state = WalRecoveryState::new(true);
+        state.record_segment_sequence(1, 5);
+
+        let mut applied = Vec::new();
+        state.replay(vec![(5, 1, "already-applied"), (6, 1, "new")], |op_num, op| applied.push((op_num, op))).unwrap();
+
+        assert_eq!(applied, vec![(6, "new")]);
+    }
+
+    #[test]
+    fn test_replay_catches_up_a_lagging_segment_independently() {
+        let mut state = WalRecoveryState::new(true);
+        state.record_segment_sequence(1, 3);
+        state.record_segment_sequence(2, 7);
+
+        let mut applied = Vec::new();
+        state.replay(
+            vec![
+                (4, 1, "seg1-op4"),
+                (5, 2, "seg2-op5-already-applied"),
+                (6, 2, "seg2-op6-already-applied"),
+                (7, 2, "seg2-op7-already-applied"),
+                (8, 2, "seg2-op8"),
+            ],
+            |op_num, op| applied.push((op_num, op)),
+        ).unwrap();
+
+        assert_eq!(applied, vec![(4, "seg1-op4"), (8, "seg2-op8")]);
+    }
+}
@@ -0,0 +1,141 @@
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+use parking_lot::{Mutex, RwLock};
+use roaring::RoaringTreemap;
+use segment::types::{PointIdType, SeqNumberType};
+
+/// Append-only log of point deletions, shared via `Arc` so a `ProxySegment`
+/// and any optimization threads touching the same wrapped segment observe
+/// the exact same sequence of deletes without copying it around.
+#[derive(Default)]
+pub struct DeleteQueue {
+    entries: RwLock<Vec<(SeqNumberType, PointIdType)>>,
+}
+
+pub type LockedDeleteQueue = Arc<DeleteQueue>;
+
+impl DeleteQueue {
+    pub fn new() -> LockedDeleteQueue {
+        Arc::new(Self::default())
+    }
+
+    pub fn push(&self, op_num: SeqNumberType, point_id: PointIdType) {
+        self.entries.write().push((op_num, point_id));
+    }
+
+    fn len(&self) -> usize {
+        self.entries.read().len()
+    }
+
+    fn entries_from(&self, cursor: usize) -> Vec<(SeqNumberType, PointIdType)> {
+        self.entries.read()[cursor..].to_vec()
+    }
+}
+
+/// Everything a `DeleteCursor` has absorbed so far, swapped in as a whole so
+/// reads never need to take a lock.
+#[derive(Default)]
+struct CursorState {
+    absorbed: usize,
+    excluded: RoaringTreemap,
+}
+
+/// A segment-local cursor into a shared `DeleteQueue`. The absorbed bitset
+/// lives behind an `ArcSwap`, so `contains`/`is_empty`/`len` never block a
+/// concurrent reader; only catching up to new entries takes a brief, rare
+/// lock. `contains` is an O(1) bitset lookup, so callers can test candidates
+/// one at a time instead of building a `Filter`/`Condition` from the set.
+pub struct DeleteCursor {
+    queue: LockedDeleteQueue,
+    state: ArcSwap<CursorState>,
+    advance_lock: Mutex<()>,
+}
+
+impl DeleteCursor {
+    pub fn new(queue: LockedDeleteQueue) -> Self {
+        DeleteCursor {
+            queue,
+            state: ArcSwap::from_pointee(CursorState::default()),
+            advance_lock: Mutex::new(()),
+        }
+    }
+
+    fn advance(&self) {
+        let queue_len = self.queue.len();
+        if queue_len == self.state.load().absorbed {
+            return;
+        }
+
+        let _guard = self.advance_lock.lock();
+        let current = self.state.load();
+        if queue_len <= current.absorbed {
+            return;
+        }
+
+        let mut excluded = current.excluded.clone();
+        for (_, point_id) in self.queue.entries_from(current.absorbed) {
+            excluded.insert(point_id);
+        }
+
+        self.state.store(Arc::new(CursorState { absorbed: queue_len, excluded }));
+    }
+
+    pub fn contains(&self, point_id: PointIdType) -> bool {
+        self.advance();
+        self.state.load().excluded.contains(point_id)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.advance();
+        self.state.load().excluded.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.advance();
+        self.state.load().excluded.len() as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_absorbs_only_new_entries() {
+        let queue = DeleteQueue::new();
+        let cursor = DeleteCursor::new(queue.clone());
+
+        queue.push(1, 10);
+        assert!(cursor.contains(10));
+        assert!(!cursor.contains(20));
+
+        queue.push(2, 20);
+        assert!(cursor.contains(20));
+    }
+
+    #[test]
+    fn test_cursors_share_the_same_queue() {
+        let queue = DeleteQueue::new();
+        let first = DeleteCursor::new(queue.clone());
+        let second = DeleteCursor::new(queue.clone());
+
+        queue.push(1, 5);
+
+        assert!(first.contains(5));
+        assert!(second.contains(5));
+        assert_eq!(first.len(), second.len());
+    }
+
+    #[test]
+    fn test_is_empty_reflects_new_deletes() {
+        let queue = DeleteQueue::new();
+        let cursor = DeleteCursor::new(queue.clone());
+
+        assert!(cursor.is_empty());
+
+        queue.push(1, 1);
+        assert!(!cursor.is_empty());
+        assert!(cursor.contains(1));
+    }
+}
@@ -1,13 +1,61 @@
 use segment::entry::entry_point::{SegmentEntry, OperationResult};
-use segment::types::{Filter, Condition, SearchParams, ScoredPoint, PayloadKeyType, PayloadType, TheMap, SeqNumberType, VectorElementType, PointIdType, SegmentInfo, SegmentType, SegmentConfig};
+use segment::types::{Filter, SearchParams, ScoredPoint, PayloadKeyType, PayloadType, TheMap, SeqNumberType, VectorElementType, PointIdType, SegmentInfo, SegmentType, SegmentConfig};
 use std::cmp::max;
 use crate::segment_manager::holders::segment_holder::LockedSegment;
+use crate::segment_manager::holders::delete_queue::{DeleteCursor, LockedDeleteQueue};
 use std::collections::HashSet;
 use std::sync::Arc;
-use parking_lot::RwLock;
+use parking_lot::{RwLock, RwLockReadGuard};
+use ouroboros::self_referencing;
 
-type LockedRmSet = Arc<RwLock<HashSet<PointIdType>>>;
 type LockedFieldsSet = Arc<RwLock<HashSet<PayloadKeyType>>>;
+type LockedSegmentEntry = Arc<RwLock<Box<dyn SegmentEntry>>>;
+
+
+/// Owns the `read()` guards of both segments together with the `iter_points()`
+/// iterators borrowed from them.
+#[self_referencing]
+struct ProxyPointsIterator {
+    wrapped_segment: LockedSegmentEntry,
+    write_segment: LockedSegmentEntry,
+    #[borrows(wrapped_segment)]
+    #[covariant]
+    wrapped_guard: RwLockReadGuard<'this, Box<dyn SegmentEntry>>,
+    #[borrows(write_segment)]
+    #[covariant]
+    write_guard: RwLockReadGuard<'this, Box<dyn SegmentEntry>>,
+    #[borrows(wrapped_guard)]
+    #[covariant]
+    wrapped_iterator: Box<dyn Iterator<Item=PointIdType> + 'this>,
+    #[borrows(write_guard)]
+    #[covariant]
+    write_iterator: Box<dyn Iterator<Item=PointIdType> + 'this>,
+}
+
+/// Chains wrapped_segment's points (skipping moved ones) with write_segment's.
+struct ProxyPointsIteratorImpl {
+    inner: ProxyPointsIterator,
+    deleted_cursor: DeleteCursor,
+    in_write_segment: bool,
+}
+
+impl Iterator for ProxyPointsIteratorImpl {
+    type Item = PointIdType;
+
+    fn next(&mut self) -> Option<PointIdType> {
+        if !self.in_write_segment {
+            let deleted_cursor = &self.deleted_cursor;
+            let next_wrapped = self.inner.with_wrapped_iterator_mut(|iter| {
+                iter.find(|point_id| !deleted_cursor.contains(*point_id))
+            });
+            if let Some(point_id) = next_wrapped {
+                return Some(point_id);
+            }
+            self.in_write_segment = true;
+        }
+        self.inner.with_write_iterator_mut(|iter| iter.next())
+    }
+}
 
 
 /// This object is a wrapper around read-only segment.
@@ -16,8 +64,10 @@ type LockedFieldsSet = Arc<RwLock<HashSet<PayloadKeyType>>>;
 pub struct ProxySegment {
     pub write_segment: LockedSegment,
     pub wrapped_segment: LockedSegment,
-    /// Points which should not longer used from wrapped_segment
-    deleted_points: LockedRmSet,
+    /// Points which should no longer be used from wrapped_segment
+    delete_queue: LockedDeleteQueue,
+    /// This segment's own view of `delete_queue`, folded in incrementally.
+    deleted_cursor: DeleteCursor,
     deleted_indexes: LockedFieldsSet,
     created_indexes: LockedFieldsSet
 }
@@ -27,14 +77,16 @@ impl ProxySegment {
     pub fn new(
         segment: LockedSegment,
         write_segment: LockedSegment,
-        deleted_points: LockedRmSet,
+        delete_queue: LockedDeleteQueue,
         created_indexes: LockedFieldsSet,
         deleted_indexes: LockedFieldsSet,
     ) -> Self {
+        let deleted_cursor = DeleteCursor::new(delete_queue.clone());
         ProxySegment {
             write_segment,
             wrapped_segment: segment,
-            deleted_points,
+            delete_queue,
+            deleted_cursor,
             created_indexes,
             deleted_indexes
         }
@@ -47,8 +99,7 @@ impl ProxySegment {
             (segment.vector(point_id)?, segment.payload(point_id)?)
         };
 
-        let mut deleted_points = self.deleted_points.write();
-        deleted_points.insert(point_id);
+        self.delete_queue.push(op_num, point_id);
 
         let segment_arc = self.write_segment.get();
         let mut write_segment = segment_arc.write();
@@ -61,7 +112,7 @@ impl ProxySegment {
 
     fn move_if_exists(&self, op_num: SeqNumberType, point_id: PointIdType) -> OperationResult<bool> {
         let wrapped_has_point = self.wrapped_segment.get().read().has_point(point_id);
-        let already_deleted = self.deleted_points.read().contains(&point_id);
+        let already_deleted = self.deleted_cursor.contains(point_id);
         if wrapped_has_point && !already_deleted {
             return self.move_point(op_num, point_id);
         }
@@ -78,50 +129,29 @@ impl SegmentEntry for ProxySegment {
     }
 
     fn search(&self, vector: &Vec<VectorElementType>, filter: Option<&Filter>, top: usize, params: Option<&SearchParams>) -> OperationResult<Vec<ScoredPoint>> {
-        let deleted_points = self.deleted_points.read();
-
-        // Some point might be deleted after temporary segment creation
-        // We need to prevent them from being found by search request
-        // That is why we need to pass additional filter for deleted points
-        let do_update_filter = !deleted_points.is_empty();
-        let mut wrapped_result = if do_update_filter {
-            // ToDo: Come up with better way to pass deleted points into Filter
-            // e.g. implement AtomicRefCell for Serializer.
-            // This copy might slow process down if there will be a lot of deleted points
-            let wrapper_condition = Condition::HasId(deleted_points.clone().into());
-            let wrapped_filter = match filter {
-                None => {
-                    Some(Filter::new_must_not(wrapper_condition))
-                }
-                Some(f) => {
-                    let mut new_filter = f.clone();
-                    let must_not = new_filter.must_not;
-
-                    let new_must_not = match must_not {
-                        None => Some(vec![wrapper_condition]),
-                        Some(mut conditions) => {
-                            conditions.push(wrapper_condition);
-                            Some(conditions)
-                        }
-                    };
-                    new_filter.must_not = new_must_not;
-                    Some(new_filter)
-                }
-            };
-
-            self.wrapped_segment.get().read().search(
-                vector,
-                wrapped_filter.as_ref(),
-                top,
-                params,
-            )?
+        // Some points might be deleted after wrapped_segment was frozen. Rather
+        // than building a `must_not` Filter/Condition from the whole exclusion
+        // set on every call, over-fetch a few extra candidates and reject
+        // excluded ids directly off deleted_cursor's bitset (an O(1) check).
+        let mut wrapped_result = if self.deleted_cursor.is_empty() {
+            self.wrapped_segment.get().read().search(vector, filter, top, params)?
         } else {
-            self.wrapped_segment.get().read().search(
-                vector,
-                filter,
-                top,
-                params,
-            )?
+            let mut fetch = top;
+            let mut kept = Vec::with_capacity(top);
+            loop {
+                let candidates = self.wrapped_segment.get().read().search(vector, filter, fetch, params)?;
+                let exhausted = candidates.len() < fetch;
+                kept = candidates
+                    .into_iter()
+                    .filter(|scored| !self.deleted_cursor.contains(scored.id))
+                    .collect();
+                if kept.len() >= top || exhausted {
+                    break;
+                }
+                fetch *= 2;
+            }
+            kept.truncate(top);
+            kept
         };
 
         let mut write_result = self.write_segment.get().read().search(
@@ -145,7 +175,7 @@ impl SegmentEntry for ProxySegment {
         if self.version() > op_num { return Ok(false); }
         let mut was_deleted = false;
         if self.wrapped_segment.get().read().has_point(point_id) {
-            self.deleted_points.write().insert(point_id);
+            self.delete_queue.push(op_num, point_id);
             was_deleted = true;
         }
         let was_deleted_in_writable = self.write_segment.get().write().delete_point(op_num, point_id)?;
@@ -179,7 +209,7 @@ impl SegmentEntry for ProxySegment {
     }
 
     fn vector(&self, point_id: PointIdType) -> OperationResult<Vec<VectorElementType>> {
-        return if self.deleted_points.read().contains(&point_id) {
+        return if self.deleted_cursor.contains(point_id) {
             self.write_segment.get().read().vector(point_id)
         } else {
             self.wrapped_segment.get().read().vector(point_id)
@@ -187,22 +217,32 @@ impl SegmentEntry for ProxySegment {
     }
 
     fn payload(&self, point_id: PointIdType) -> OperationResult<TheMap<PayloadKeyType, PayloadType>> {
-        return if self.deleted_points.read().contains(&point_id) {
+        return if self.deleted_cursor.contains(point_id) {
             self.write_segment.get().read().payload(point_id)
         } else {
             self.wrapped_segment.get().read().payload(point_id)
         };
     }
 
-    /// Not implemented for proxy
     fn iter_points(&self) -> Box<dyn Iterator<Item=u64> + '_> {
-        // iter_points is not available for Proxy implementation
-        // Due to internal locks it is almost impossible to return iterator with proper owning, lifetimes, e.t.c.
-        unimplemented!()
+        let iterator = ProxyPointsIteratorBuilder {
+            wrapped_segment: self.wrapped_segment.get(),
+            write_segment: self.write_segment.get(),
+            wrapped_guard_builder: |wrapped_segment: &LockedSegmentEntry| wrapped_segment.read(),
+            write_guard_builder: |write_segment: &LockedSegmentEntry| write_segment.read(),
+            wrapped_iterator_builder: |wrapped_guard: &RwLockReadGuard<Box<dyn SegmentEntry>>| wrapped_guard.iter_points(),
+            write_iterator_builder: |write_guard: &RwLockReadGuard<Box<dyn SegmentEntry>>| write_guard.iter_points(),
+        }.build();
+
+        Box::new(ProxyPointsIteratorImpl {
+            inner: iterator,
+            deleted_cursor: DeleteCursor::new(self.delete_queue.clone()),
+            in_write_segment: false,
+        })
     }
 
     fn has_point(&self, point_id: PointIdType) -> bool {
-        return if self.deleted_points.read().contains(&point_id) {
+        return if self.deleted_cursor.contains(point_id) {
             self.write_segment.get().read().has_point(point_id)
         } else {
             self.wrapped_segment.get().read().has_point(point_id)
@@ -212,7 +252,7 @@ impl SegmentEntry for ProxySegment {
     fn vectors_count(&self) -> usize {
         let mut count = 0;
         count += self.wrapped_segment.get().read().vectors_count();
-        count -= self.deleted_points.read().len();
+        count -= self.deleted_cursor.len();
         count += self.write_segment.get().read().vectors_count();
         count
     }
@@ -293,7 +333,7 @@ mod tests {
         let dir = TempDir::new("segment_dir").unwrap();
         let original_segment = LockedSegment::new(build_segment_1(dir.path()));
         let write_segment = LockedSegment::new(empty_segment(dir.path()));
-        let deleted_points = Arc::new(RwLock::new(HashSet::<PointIdType>::new()));
+        let delete_queue = crate::segment_manager::holders::delete_queue::DeleteQueue::new();
 
         let deleted_indexes = Arc::new(RwLock::new(HashSet::<PayloadKeyType>::new()));
         let created_indexes = Arc::new(RwLock::new(HashSet::<PayloadKeyType>::new()));
@@ -301,7 +341,7 @@ mod tests {
         let mut proxy_segment = ProxySegment::new(
             original_segment,
             write_segment,
-            deleted_points,
+            delete_queue,
             deleted_indexes.clone(),
             created_indexes.clone()
         );
@@ -338,4 +378,36 @@ mod tests {
 
         assert!(proxy_segment.write_segment.get().read().has_point(2))
     }
+
+    #[test]
+    fn test_iter_points() {
+        let dir = TempDir::new("segment_dir").unwrap();
+        let original_segment = LockedSegment::new(build_segment_1(dir.path()));
+        let write_segment = LockedSegment::new(empty_segment(dir.path()));
+        let delete_queue = crate::segment_manager::holders::delete_queue::DeleteQueue::new();
+
+        let deleted_indexes = Arc::new(RwLock::new(HashSet::<PayloadKeyType>::new()));
+        let created_indexes = Arc::new(RwLock::new(HashSet::<PayloadKeyType>::new()));
+
+        let mut proxy_segment = ProxySegment::new(
+            original_segment,
+            write_segment,
+            delete_queue,
+            deleted_indexes.clone(),
+            created_indexes.clone()
+        );
+
+        let vec4 = vec![1.1, 1.0, 0.0, 1.0];
+        proxy_segment.upsert_point(100, 4, &vec4).unwrap();
+        proxy_segment.delete_point(101, 1).unwrap();
+
+        let mut seen_points: HashSet<PointIdType> = Default::default();
+        for point_id in proxy_segment.iter_points() {
+            assert!(!seen_points.contains(&point_id), "point {} appears multiple times", point_id);
+            seen_points.insert(point_id);
+        }
+
+        assert!(seen_points.contains(&4));
+        assert!(!seen_points.contains(&1));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,83 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use segment::types::{SegmentConfig, SegmentInfo};
+
+use crate::segment_manager::holders::segment_holder::{LockedSegmentHolder, SegmentId};
+use crate::segment_manager::optimizers::merge_policy::{MergeCandidate, MergePolicy};
+use crate::segment_manager::optimizers::segment_optimizer::{OptimizerThresholds, SegmentOptimizer};
+
+/// Picks which segments to merge on each pass: `merge_policy` decides,
+/// `check_condition` resolves one of its picks (a by-index `MergeCandidate`)
+/// to real `SegmentId`s, leaving the others for later passes, and `optimize()` -- the default provided by
+/// `SegmentOptimizer` -- does the actual merge.
+pub struct MergeOptimizer {
+    merge_policy: Box<dyn MergePolicy>,
+    thresholds: OptimizerThresholds,
+    segments_path: PathBuf,
+    temp_segments_path: PathBuf,
+    segment_config: SegmentConfig,
+}
+
+impl MergeOptimizer {
+    pub fn new(
+        merge_policy: Box<dyn MergePolicy>,
+        thresholds: OptimizerThresholds,
+        segments_path: PathBuf,
+        temp_segments_path: PathBuf,
+        segment_config: SegmentConfig,
+    ) -> Self {
+        MergeOptimizer {
+            merge_policy,
+            thresholds,
+            segments_path,
+            temp_segments_path,
+            segment_config,
+        }
+    }
+
+    /// Candidates to merge on this optimization pass, as decided by `merge_policy`.
+    pub fn compute_merge_candidates(&self, segments: &[SegmentInfo]) -> Vec<MergeCandidate> {
+        self.merge_policy.compute_merge_candidates(segments)
+    }
+}
+
+impl SegmentOptimizer for MergeOptimizer {
+    fn collection_path(&self) -> &std::path::Path {
+        self.segments_path.as_path()
+    }
+
+    fn temp_path(&self) -> &std::path::Path {
+        self.temp_segments_path.as_path()
+    }
+
+    fn segment_config(&self) -> &SegmentConfig {
+        &self.segment_config
+    }
+
+    fn threshold_config(&self) -> &OptimizerThresholds {
+        &self.thresholds
+    }
+
+    fn check_condition(
+        &self,
+        segments: LockedSegmentHolder,
+        excluded_ids: &HashSet<SegmentId>,
+    ) -> Vec<SegmentId> {
+        let segments_read = segments.read();
+        let (ids, infos): (Vec<SegmentId>, Vec<SegmentInfo>) = segments_read
+            .iter()
+            .filter(|(id, _)| !excluded_ids.contains(id))
+            .map(|(id, segment)| (*id, segment.get().read().info()))
+            .unzip();
+
+        self.compute_merge_candidates(&infos)
+            .into_iter()
+            .next()
+            .map(|candidate| candidate.segment_indices)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|index| ids.get(index).copied())
+            .collect()
+    }
+}
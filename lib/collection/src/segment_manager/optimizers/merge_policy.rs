@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+
+use schemars::JsonSchema;
+use segment::types::SegmentInfo;
+use serde::{Deserialize, Serialize};
+
+/// A group of segments, by index into the slice passed to
+/// `MergePolicy::compute_merge_candidates`, that should be merged together.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeCandidate {
+    pub segment_indices: Vec<usize>,
+}
+
+/// Selects which segments to merge; `MergeOptimizer` executes the merge.
+pub trait MergePolicy: Send + Sync {
+    fn compute_merge_candidates(&self, segments: &[SegmentInfo]) -> Vec<MergeCandidate>;
+}
+
+fn default_level_base() -> usize { 10 }
+fn default_min_level_size() -> usize { 1 }
+fn default_min_merge_segments() -> usize { 2 }
+fn default_max_merged_segment_size() -> usize { usize::MAX }
+
+/// Tiered merge policy: segments are bucketed into exponential size levels by
+/// `num_vectors` (`level = floor(log_base(count))`); any level with at least
+/// `min_merge_segments` segments is emitted as a candidate, capped at
+/// `max_merged_segment_size`.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct LogMergePolicy {
+    #[serde(default = "default_level_base")]
+    pub level_base: usize,
+    #[serde(default = "default_min_level_size")]
+    pub min_level_size: usize,
+    #[serde(default = "default_min_merge_segments")]
+    pub min_merge_segments: usize,
+    #[serde(default = "default_max_merged_segment_size")]
+    pub max_merged_segment_size: usize,
+}
+
+impl Default for LogMergePolicy {
+    fn default() -> Self {
+        LogMergePolicy {
+            level_base: default_level_base(),
+            min_level_size: default_min_level_size(),
+            min_merge_segments: default_min_merge_segments(),
+            max_merged_segment_size: default_max_merged_segment_size(),
+        }
+    }
+}
+
+impl LogMergePolicy {
+    fn level_of(&self, num_vectors: usize) -> usize {
+        // Integer division rather than `f64::log`, which puts exact powers
+        // such as 1000 one level too low (`1000f64.log(10.0)` is 2.9999999999999996).
+        let mut size = num_vectors.max(self.min_level_size);
+        let mut level = 0;
+        while self.level_base > 1 && size >= self.level_base {
+            size /= self.level_base;
+            level += 1;
+        }
+        level
+    }
+}
+
+impl MergePolicy for LogMergePolicy {
+    fn compute_merge_candidates(&self, segments: &[SegmentInfo]) -> Vec<MergeCandidate> {
+        let mut levels: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (index, segment) in segments.iter().enumerate() {
+            levels.entry(self.level_of(segment.num_vectors)).or_default().push(index);
+        }
+
+        levels
+            .into_values()
+            .filter(|indices| indices.len() >= self.min_merge_segments)
+            .filter_map(|indices| {
+                let mut merged_size = 0usize;
+                let mut segment_indices = Vec::new();
+                for index in indices {
+                    let size = segments[index].num_vectors;
+                    if !segment_indices.is_empty() && merged_size + size > self.max_merged_segment_size {
+                        break;
+                    }
+                    merged_size += size;
+                    segment_indices.push(index);
+                }
+                if segment_indices.len() >= self.min_merge_segments {
+                    Some(MergeCandidate { segment_indices })
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+/// Active merge policy and its parameters, as configured in `OptimizersConfig`.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MergePolicyConfig {
+    Log(LogMergePolicy),
+}
+
+impl Default for MergePolicyConfig {
+    fn default() -> Self {
+        MergePolicyConfig::Log(LogMergePolicy::default())
+    }
+}
+
+impl MergePolicyConfig {
+    pub fn build(&self) -> Box<dyn MergePolicy> {
+        match self {
+            MergePolicyConfig::Log(policy) => Box::new(policy.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment_info(num_vectors: usize) -> SegmentInfo {
+        SegmentInfo {
+            segment_type: segment::types::SegmentType::Plain,
+            num_vectors,
+            num_deleted_vectors: 0,
+            ram_usage_bytes: 0,
+            disk_usage_bytes: 0,
+            is_appendable: true,
+            schema: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_buckets_by_level() {
+        let policy = LogMergePolicy::default();
+        let segments = vec![
+            segment_info(5),
+            segment_info(8),
+            segment_info(500),
+            segment_info(800),
+        ];
+
+        let candidates = policy.compute_merge_candidates(&segments);
+        assert_eq!(candidates.len(), 2);
+        for candidate in &candidates {
+            assert_eq!(candidate.segment_indices.len(), 2);
+        }
+    }
+
+    #[test]
+    fn test_respects_min_merge_segments() {
+        let policy = LogMergePolicy { min_merge_segments: 3, ..Default::default() };
+        let segments = vec![segment_info(5), segment_info(8)];
+
+        assert!(policy.compute_merge_candidates(&segments).is_empty());
+    }
+
+    #[test]
+    fn test_caps_merged_segment_size() {
+        let policy = LogMergePolicy { max_merged_segment_size: 10, ..Default::default() };
+        let segments = vec![segment_info(5), segment_info(5), segment_info(5)];
+
+        let candidates = policy.compute_merge_candidates(&segments);
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].segment_indices.len(), 2);
+    }
+}
@@ -5,6 +5,12 @@ use std::env;
 #[derive(Debug, Deserialize)]
 pub struct StorageConfig {
     pub wal: String,
+    /// Zstd-compress snapshots written for fast restart (see `collection_builder::snapshot`).
+    #[serde(default)]
+    pub snapshot_compression: bool,
+    /// Fail fast on a non-contiguous WAL op_num sequence instead of logging and continuing.
+    #[serde(default)]
+    pub strict_wal_recovery: bool,
 }
 
 #[derive(Debug, Deserialize)]